@@ -1,145 +1,502 @@
-use sha2::{Sha256, Digest};
-use std::time::{SystemTime, UNIX_EPOCH};
-use std::fmt;
-use serde::{Serialize, Deserialize};
-
-#[derive(Clone, Serialize, Deserialize)]
-pub struct Block {
-    pub index: usize,
-    pub timestamp: u64,
-    pub data: String,
-    pub previous_hash: String,
-    pub hash: String,
-    pub nonce: u64,
-}
-
-impl fmt::Display for Block {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Block #{} [Hash: {}...] - Data: {}", 
-               self.index, 
-               &self.hash[..10], 
-               if self.data.len() > 50 { 
-                   format!("{}...", &self.data[..50]) 
-               } else { 
-                   self.data.clone() 
-               })
-    }
-}
-
-impl Block {
-    pub fn new(index: usize, timestamp: u64, data: String, previous_hash: String) -> Self {
-        let mut block = Block {
-            index,
-            timestamp,
-            data,
-            previous_hash,
-            hash: String::new(),
-            nonce: 0,
-        };
-        
-        block.mine(2); // Difficulty level 2 (two leading zeros)
-        block
-    }
-    
-    pub fn calculate_hash(&self) -> String {
-        let data = format!("{}{}{}{}{}", 
-                          self.index, 
-                          self.timestamp, 
-                          self.data, 
-                          self.previous_hash,
-                          self.nonce);
-        
-        let mut hasher = Sha256::new();
-        hasher.update(data.as_bytes());
-        format!("{:x}", hasher.finalize())
-    }
-    
-    fn mine(&mut self, difficulty: usize) {
-        let target = "0".repeat(difficulty);
-        
-        loop {
-            self.hash = self.calculate_hash();
-            if self.hash.starts_with(&target) {
-                break;
-            }
-            self.nonce += 1;
-        }
-        
-        println!("Block mined: {}", self.hash);
-    }
-}
-
-pub struct Blockchain {
-    pub chain: Vec<Block>,
-}
-
-impl Blockchain {
-    pub fn new() -> Self {
-        let mut blockchain = Blockchain {
-            chain: Vec::new(),
-        };
-        
-        // Create genesis block
-        let genesis_block = Block::new(
-            0,
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            String::from("Genesis Block"),
-            String::from("0"),
-        );
-        
-        blockchain.chain.push(genesis_block);
-        blockchain
-    }
-    
-    pub fn add_block(&mut self, data: String) -> &Block {
-        let previous_block = self.chain.last().unwrap();
-        let new_block = Block::new(
-            previous_block.index + 1,
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            data,
-            previous_block.hash.clone(),
-        );
-        
-        self.chain.push(new_block);
-        self.chain.last().unwrap()
-    }
-    
-    pub fn is_valid(&self) -> bool {
-        for i in 1..self.chain.len() {
-            let current_block = &self.chain[i];
-            let previous_block = &self.chain[i - 1];
-            
-            // Verify hash
-            if current_block.hash != current_block.calculate_hash() {
-                println!("Invalid hash for block {}", current_block.index);
-                return false;
-            }
-            
-            // Verify chain link
-            if current_block.previous_hash != previous_block.hash {
-                println!("Invalid chain link at block {}", current_block.index);
-                return false;
-            }
-        }
-        
-        true
-    }
-    
-    pub fn get_all_blocks(&self) -> Vec<&Block> {
-        self.chain.iter().collect()
-    }
-    
-    pub fn get_blockchain_data(&self) -> Vec<String> {
-        self.chain.iter().map(|block| block.data.clone()).collect()
-    }
-    
-    pub fn get_chain_length(&self) -> usize {
-        self.chain.len()
-    }
+use sha2::{Sha256, Digest};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use crate::iot_device::SensorData;
+use crate::consensus::{Consensus, ProofOfWork};
+use crate::events::{ChainEvent, PipelineStatus};
+use tokio::sync::broadcast;
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+#[derive(Debug)]
+pub struct DecodeError(String);
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "block decode error: {}", self.0)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+fn encode_string(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(&(s.len() as u32).to_be_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn decode_u64(bytes: &[u8], cursor: &mut usize) -> Result<u64, DecodeError> {
+    let end = *cursor + 8;
+    let chunk = bytes.get(*cursor..end)
+        .ok_or_else(|| DecodeError("unexpected end of input reading a u64".to_string()))?;
+    *cursor = end;
+    Ok(u64::from_be_bytes(chunk.try_into().unwrap()))
+}
+
+fn decode_string(bytes: &[u8], cursor: &mut usize) -> Result<String, DecodeError> {
+    let len_end = *cursor + 4;
+    let len_bytes = bytes.get(*cursor..len_end)
+        .ok_or_else(|| DecodeError("unexpected end of input reading a length prefix".to_string()))?;
+    let len = u32::from_be_bytes(len_bytes.try_into().unwrap()) as usize;
+    *cursor = len_end;
+
+    let str_end = *cursor + len;
+    let str_bytes = bytes.get(*cursor..str_end)
+        .ok_or_else(|| DecodeError("unexpected end of input reading a length-prefixed string".to_string()))?;
+    *cursor = str_end;
+
+    String::from_utf8(str_bytes.to_vec()).map_err(|e| DecodeError(format!("invalid utf8: {}", e)))
+}
+
+fn merkle_root(leaf_hashes: &[String]) -> String {
+    if leaf_hashes.is_empty() {
+        return sha256_hex(b"");
+    }
+
+    let mut level = leaf_hashes.to_vec();
+    while level.len() > 1 {
+        let mut next_level = Vec::with_capacity((level.len() + 1) / 2);
+        for pair in level.chunks(2) {
+            let left = &pair[0];
+            let right = if pair.len() == 2 { &pair[1] } else { left };
+            next_level.push(sha256_hex(format!("{}{}", left, right).as_bytes()));
+        }
+        level = next_level;
+    }
+    level.remove(0)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Block {
+    pub index: usize,
+    pub timestamp: u64,
+    pub data: String,
+    pub previous_hash: String,
+    pub hash: String,
+    pub nonce: u64,
+    pub merkle_root: String,
+    pub readings: Vec<SensorData>,
+    pub signature: Option<String>,
+    pub validator: Option<String>,
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Block #{} [Hash: {}...] - Data: {}", 
+               self.index, 
+               &self.hash[..10], 
+               if self.data.len() > 50 { 
+                   format!("{}...", &self.data[..50]) 
+               } else { 
+                   self.data.clone() 
+               })
+    }
+}
+
+impl Block {
+    pub fn new(index: usize, timestamp: u64, data: String, previous_hash: String) -> Self {
+        Block::new_with_readings(index, timestamp, data, previous_hash, Vec::new())
+    }
+
+    // Batches `readings` into the block as Merkle leaves so a single reading can later be proven
+    // via `generate_proof`. Returns the block unsealed; call a `Consensus::seal` on it first.
+    pub fn new_with_readings(
+        index: usize,
+        timestamp: u64,
+        data: String,
+        previous_hash: String,
+        readings: Vec<SensorData>,
+    ) -> Self {
+        let merkle_root = if readings.is_empty() {
+            sha256_hex(data.as_bytes())
+        } else {
+            let leaf_hashes: Vec<String> = readings.iter()
+                .map(|reading| sha256_hex(reading.to_json().as_bytes()))
+                .collect();
+            merkle_root(&leaf_hashes)
+        };
+
+        Block {
+            index,
+            timestamp,
+            data,
+            previous_hash,
+            hash: String::new(),
+            nonce: 0,
+            merkle_root,
+            readings,
+            signature: None,
+            validator: None,
+        }
+    }
+
+    // Length-prefixed binary layout of (index, timestamp, data, previous_hash, nonce,
+    // merkle_root); unlike string concatenation, different field splits never collide on bytes.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&(self.index as u64).to_be_bytes());
+        buf.extend_from_slice(&self.timestamp.to_be_bytes());
+        encode_string(&mut buf, &self.data);
+        encode_string(&mut buf, &self.previous_hash);
+        buf.extend_from_slice(&self.nonce.to_be_bytes());
+        encode_string(&mut buf, &self.merkle_root);
+        buf
+    }
+
+    // Inverse of `encode`. The hash is recomputed; readings/signature/validator aren't part of
+    // the canonical encoding, so they come back empty/`None`.
+    pub fn decode(bytes: &[u8]) -> Result<Block, DecodeError> {
+        let mut cursor = 0usize;
+
+        let index = decode_u64(bytes, &mut cursor)? as usize;
+        let timestamp = decode_u64(bytes, &mut cursor)?;
+        let data = decode_string(bytes, &mut cursor)?;
+        let previous_hash = decode_string(bytes, &mut cursor)?;
+        let nonce = decode_u64(bytes, &mut cursor)?;
+        let merkle_root = decode_string(bytes, &mut cursor)?;
+
+        if cursor != bytes.len() {
+            return Err(DecodeError("trailing bytes after a complete block encoding".to_string()));
+        }
+
+        let mut block = Block {
+            index,
+            timestamp,
+            data,
+            previous_hash,
+            hash: String::new(),
+            nonce,
+            merkle_root,
+            readings: Vec::new(),
+            signature: None,
+            validator: None,
+        };
+        block.hash = block.calculate_hash();
+        Ok(block)
+    }
+
+    pub fn calculate_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.encode());
+        format!("{:x}", hasher.finalize())
+    }
+
+    pub(crate) fn mine(&mut self, difficulty: usize) {
+        let target = "0".repeat(difficulty);
+        
+        loop {
+            self.hash = self.calculate_hash();
+            if self.hash.starts_with(&target) {
+                break;
+            }
+            self.nonce += 1;
+        }
+        
+        println!("Block mined: {}", self.hash);
+    }
+}
+
+pub fn verify_proof(leaf_hash: &str, proof: &[(String, bool)], root: &str) -> bool {
+    let mut current = leaf_hash.to_string();
+
+    for (sibling, sibling_is_left) in proof {
+        current = if *sibling_is_left {
+            sha256_hex(format!("{}{}", sibling, current).as_bytes())
+        } else {
+            sha256_hex(format!("{}{}", current, sibling).as_bytes())
+        };
+    }
+
+    current == root
+}
+
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+pub struct Blockchain {
+    pub chain: Vec<Block>,
+    consensus: Box<dyn Consensus>,
+    events: broadcast::Sender<ChainEvent>,
+}
+
+impl Blockchain {
+    // Defaults to PoW sealing; use `with_consensus` for PoA or another `Consensus` impl.
+    pub fn new() -> Self {
+        Self::with_consensus(Box::new(ProofOfWork { difficulty: 2 }))
+            .expect("ProofOfWork can always seal the genesis block")
+    }
+
+    pub fn with_consensus(consensus: Box<dyn Consensus>) -> Result<Self, String> {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let mut blockchain = Blockchain {
+            chain: Vec::new(),
+            consensus,
+            events,
+        };
+
+        // Create genesis block
+        let mut genesis_block = Block::new(
+            0,
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            String::from("Genesis Block"),
+            String::from("0"),
+        );
+        blockchain.consensus.seal(&mut genesis_block)?;
+
+        blockchain.chain.push(genesis_block);
+        Ok(blockchain)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ChainEvent> {
+        self.events.subscribe()
+    }
+
+    // Clone of the sender, for things like the WebSocket server that hand out a fresh `Receiver`
+    // per connection rather than share one.
+    pub fn event_sender(&self) -> broadcast::Sender<ChainEvent> {
+        self.events.clone()
+    }
+
+    pub fn publish(&self, event: ChainEvent) {
+        let _ = self.events.send(event);
+    }
+
+    // `None` if the block was rejected (out-of-turn seal, failed consensus verification).
+    pub fn add_block(&mut self, data: String) -> Option<&Block> {
+        self.publish(ChainEvent::ValidationStatus(PipelineStatus::Validating));
+
+        let previous_block = self.chain.last().unwrap();
+        let mut new_block = Block::new(
+            previous_block.index + 1,
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            data,
+            previous_block.hash.clone(),
+        );
+
+        if let Err(reason) = self.consensus.seal(&mut new_block) {
+            self.publish(ChainEvent::ValidationStatus(PipelineStatus::Rejected(reason)));
+            return None;
+        }
+
+        self.commit(new_block).then(|| self.chain.last().unwrap())
+    }
+
+    pub fn add_readings(&mut self, readings: Vec<SensorData>) -> Option<&Block> {
+        self.publish(ChainEvent::ValidationStatus(PipelineStatus::Validating));
+
+        let previous_block = self.chain.last().unwrap();
+        let data = serde_json::to_string(&readings).unwrap_or_else(|_| "[]".to_string());
+
+        let mut new_block = Block::new_with_readings(
+            previous_block.index + 1,
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            data,
+            previous_block.hash.clone(),
+            readings,
+        );
+
+        if let Err(reason) = self.consensus.seal(&mut new_block) {
+            self.publish(ChainEvent::ValidationStatus(PipelineStatus::Rejected(reason)));
+            return None;
+        }
+
+        self.commit(new_block).then(|| self.chain.last().unwrap())
+    }
+
+    // Pushes `block` and publishes `Committed`/`BlockCommitted` if it passes consensus, otherwise
+    // leaves the chain untouched and publishes `Rejected`. Returns whether it was pushed.
+    fn commit(&mut self, block: Block) -> bool {
+        let passes_consensus = self.chain.last()
+            .map(|tip| self.consensus.verify(&block, tip))
+            .unwrap_or(true); // genesis has no predecessor to verify against
+
+        if passes_consensus {
+            self.publish(ChainEvent::ValidationStatus(PipelineStatus::Committed));
+            self.publish(ChainEvent::BlockCommitted(block.clone()));
+            self.chain.push(block);
+            true
+        } else {
+            self.publish(ChainEvent::ValidationStatus(PipelineStatus::Rejected(
+                format!("Block {} failed consensus verification", block.index),
+            )));
+            false
+        }
+    }
+
+    // Sibling hashes (with a left/right flag) to walk `leaf_index` up to the block's Merkle
+    // root; feed into `verify_proof` to check inclusion. `None` if either index is out of range.
+    pub fn generate_proof(&self, block_index: usize, leaf_index: usize) -> Option<Vec<(String, bool)>> {
+        let block = self.chain.get(block_index)?;
+        if leaf_index >= block.readings.len() {
+            return None;
+        }
+
+        let mut level: Vec<String> = block.readings.iter()
+            .map(|reading| sha256_hex(reading.to_json().as_bytes()))
+            .collect();
+
+        let mut index = leaf_index;
+        let mut proof = Vec::new();
+
+        while level.len() > 1 {
+            let pair_start = (index / 2) * 2;
+            if index % 2 == 0 {
+                let sibling_index = if pair_start + 1 < level.len() { pair_start + 1 } else { pair_start };
+                proof.push((level[sibling_index].clone(), false)); // sibling is on the right
+            } else {
+                proof.push((level[pair_start].clone(), true)); // sibling is on the left
+            }
+
+            let mut next_level = Vec::with_capacity((level.len() + 1) / 2);
+            for pair in level.chunks(2) {
+                let left = &pair[0];
+                let right = if pair.len() == 2 { &pair[1] } else { left };
+                next_level.push(sha256_hex(format!("{}{}", left, right).as_bytes()));
+            }
+            level = next_level;
+            index /= 2;
+        }
+
+        Some(proof)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        for i in 1..self.chain.len() {
+            let current_block = &self.chain[i];
+            let previous_block = &self.chain[i - 1];
+
+            if !self.consensus.verify(current_block, previous_block) {
+                println!("Consensus verification failed for block {}", current_block.index);
+                return false;
+            }
+        }
+
+        true
+    }
+    
+    pub fn get_all_blocks(&self) -> Vec<&Block> {
+        self.chain.iter().collect()
+    }
+    
+    pub fn get_blockchain_data(&self) -> Vec<String> {
+        self.chain.iter().map(|block| block.data.clone()).collect()
+    }
+    
+    pub fn get_chain_length(&self) -> usize {
+        self.chain.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(device_id: &str, temperature: f64, timestamp: u64) -> SensorData {
+        SensorData {
+            device_id: device_id.to_string(),
+            temperature,
+            humidity: 40.0,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn generate_proof_round_trips_with_verify_proof() {
+        let mut blockchain = Blockchain::new();
+        let readings = vec![
+            reading("d1", 21.0, 1),
+            reading("d2", 22.0, 2),
+            reading("d3", 23.0, 3),
+        ];
+
+        let block = blockchain.add_readings(readings.clone()).expect("readings block committed");
+        let block_index = block.index;
+        let root = block.merkle_root.clone();
+
+        for (leaf_index, r) in readings.iter().enumerate() {
+            let leaf_hash = sha256_hex(r.to_json().as_bytes());
+            let proof = blockchain.generate_proof(block_index, leaf_index)
+                .expect("proof exists for a valid leaf index");
+            assert!(verify_proof(&leaf_hash, &proof, &root));
+        }
+    }
+
+    #[test]
+    fn generate_proof_rejects_out_of_range_indices() {
+        let mut blockchain = Blockchain::new();
+        let block = blockchain.add_readings(vec![reading("d1", 21.0, 1)]).expect("readings block committed");
+        let block_index = block.index;
+
+        assert!(blockchain.generate_proof(block_index, 1).is_none());
+        assert!(blockchain.generate_proof(block_index + 10, 0).is_none());
+    }
+
+    #[test]
+    fn add_block_emits_validating_then_committed_then_block_committed() {
+        let mut blockchain = Blockchain::new();
+        let mut events = blockchain.subscribe();
+
+        blockchain.add_block("data".to_string()).expect("block committed");
+
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            ChainEvent::ValidationStatus(PipelineStatus::Validating)
+        ));
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            ChainEvent::ValidationStatus(PipelineStatus::Committed)
+        ));
+        assert!(matches!(events.try_recv().unwrap(), ChainEvent::BlockCommitted(_)));
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn commit_rejects_a_block_with_a_stale_previous_hash() {
+        let mut blockchain = Blockchain::with_consensus(Box::new(ProofOfWork { difficulty: 2 })).unwrap();
+        let before = blockchain.get_chain_length();
+
+        // previous_hash points at a tip that no longer matches the chain, so `verify` must fail.
+        let mut stale_block = Block::new(1, 1, "data".to_string(), "not-the-real-tip".to_string());
+        ProofOfWork { difficulty: 2 }.seal(&mut stale_block).unwrap();
+
+        assert!(!blockchain.commit(stale_block));
+        assert_eq!(blockchain.get_chain_length(), before);
+    }
+
+    #[test]
+    fn encode_decode_round_trips() {
+        let mut block = Block::new(3, 123456, "payload".to_string(), "prevhash".to_string());
+        block.nonce = 7;
+        block.hash = block.calculate_hash();
+
+        let decoded = Block::decode(&block.encode()).expect("a freshly encoded block decodes");
+
+        assert_eq!(decoded.index, block.index);
+        assert_eq!(decoded.timestamp, block.timestamp);
+        assert_eq!(decoded.data, block.data);
+        assert_eq!(decoded.previous_hash, block.previous_hash);
+        assert_eq!(decoded.nonce, block.nonce);
+        assert_eq!(decoded.merkle_root, block.merkle_root);
+        assert_eq!(decoded.hash, block.hash);
+    }
+
+    #[test]
+    fn decode_rejects_truncated_bytes() {
+        let block = Block::new(3, 123456, "payload".to_string(), "prevhash".to_string());
+        let encoded = block.encode();
+
+        assert!(Block::decode(&encoded[..encoded.len() - 1]).is_err());
+    }
 }
\ No newline at end of file
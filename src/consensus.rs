@@ -0,0 +1,184 @@
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+
+use crate::blockchain::Block;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.is_ascii() || s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+pub trait Consensus {
+    fn seal(&self, block: &mut Block) -> Result<(), String>;
+    fn verify(&self, block: &Block, prev: &Block) -> bool;
+}
+
+pub struct ProofOfWork {
+    pub difficulty: usize,
+}
+
+impl Consensus for ProofOfWork {
+    fn seal(&self, block: &mut Block) -> Result<(), String> {
+        block.mine(self.difficulty);
+        Ok(())
+    }
+
+    fn verify(&self, block: &Block, prev: &Block) -> bool {
+        if block.previous_hash != prev.hash {
+            return false;
+        }
+
+        if block.hash != block.calculate_hash() {
+            return false;
+        }
+
+        block.hash.starts_with(&"0".repeat(self.difficulty))
+    }
+}
+
+// Round-robin PoA: `block.index % len` picks the validator who signs the block hash.
+pub struct ProofOfAuthority {
+    validators: Vec<VerifyingKey>,
+    local_validator_index: usize,
+    signing_key: SigningKey,
+}
+
+impl ProofOfAuthority {
+    pub fn new(validators: Vec<VerifyingKey>, local_validator_index: usize, signing_key: SigningKey) -> Result<Self, String> {
+        if validators.is_empty() {
+            return Err("ProofOfAuthority requires at least one validator".to_string());
+        }
+        Ok(ProofOfAuthority {
+            validators,
+            local_validator_index,
+            signing_key,
+        })
+    }
+
+    fn validator_for(&self, index: usize) -> &VerifyingKey {
+        &self.validators[index % self.validators.len()]
+    }
+}
+
+impl Consensus for ProofOfAuthority {
+    fn seal(&self, block: &mut Block) -> Result<(), String> {
+        let expected_validator = block.index % self.validators.len();
+        if expected_validator != self.local_validator_index {
+            return Err(format!(
+                "refusing to seal block {}: it's validator {}'s turn, not ours (validator {})",
+                block.index, expected_validator, self.local_validator_index
+            ));
+        }
+
+        block.hash = block.calculate_hash();
+        let signature = self.signing_key.sign(block.hash.as_bytes());
+        block.signature = Some(to_hex(&signature.to_bytes()));
+        block.validator = Some(to_hex(self.validator_for(expected_validator).to_bytes().as_slice()));
+
+        println!("Block {} sealed by validator {}", block.index, expected_validator);
+        Ok(())
+    }
+
+    fn verify(&self, block: &Block, prev: &Block) -> bool {
+        if block.previous_hash != prev.hash {
+            return false;
+        }
+
+        if block.hash != block.calculate_hash() {
+            return false;
+        }
+
+        let expected_validator = self.validator_for(block.index);
+        let (Some(signature_hex), Some(validator_hex)) = (&block.signature, &block.validator) else {
+            return false;
+        };
+
+        if validator_hex != &to_hex(expected_validator.to_bytes().as_slice()) {
+            println!("Block {} was not sealed by the expected round-robin validator", block.index);
+            return false;
+        }
+
+        let Some(signature_bytes) = from_hex(signature_hex) else {
+            return false;
+        };
+        let Ok(signature) = Signature::from_slice(&signature_bytes) else {
+            return false;
+        };
+
+        expected_validator.verify(block.hash.as_bytes(), &signature).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn poa_signs_and_verifies_on_the_assigned_validator_turn() {
+        let key0 = test_signing_key(1);
+        let key1 = test_signing_key(2);
+        let validators = vec![key0.verifying_key(), key1.verifying_key()];
+
+        let mut genesis = Block::new(0, 0, "Genesis Block".to_string(), "0".to_string());
+        ProofOfAuthority::new(validators.clone(), 0, key0).unwrap().seal(&mut genesis).unwrap();
+
+        let mut block1 = Block::new(1, 1, "data".to_string(), genesis.hash.clone());
+        let consensus1 = ProofOfAuthority::new(validators, 1, key1).unwrap();
+        consensus1.seal(&mut block1).unwrap();
+
+        assert!(consensus1.verify(&block1, &genesis));
+    }
+
+    #[test]
+    fn poa_refuses_to_seal_out_of_turn() {
+        let key0 = test_signing_key(1);
+        let key1 = test_signing_key(2);
+        let validators = vec![key0.verifying_key(), key1.verifying_key()];
+
+        // Block index 1 belongs to validator 1's round, not validator 0's.
+        let mut block1 = Block::new(1, 1, "data".to_string(), "prev".to_string());
+        let consensus0 = ProofOfAuthority::new(validators, 0, key0).unwrap();
+
+        assert!(consensus0.seal(&mut block1).is_err());
+        assert!(block1.signature.is_none());
+    }
+
+    #[test]
+    fn from_hex_rejects_non_ascii_input_instead_of_panicking() {
+        // 4 bytes, even length, with 'é' (2 bytes) straddling the first 2-byte chunk boundary:
+        // a naive `s[0..2]` slice would cut 'é' in half instead of landing on a char boundary.
+        assert!(from_hex("aéa").is_none());
+    }
+
+    #[test]
+    fn poa_new_rejects_an_empty_validator_set() {
+        assert!(ProofOfAuthority::new(Vec::new(), 0, test_signing_key(1)).is_err());
+    }
+
+    #[test]
+    fn pow_verify_detects_a_tampered_previous_hash() {
+        let consensus = ProofOfWork { difficulty: 2 };
+
+        let mut genesis = Block::new(0, 0, "Genesis Block".to_string(), "0".to_string());
+        consensus.seal(&mut genesis).unwrap();
+
+        let mut block1 = Block::new(1, 1, "data".to_string(), genesis.hash.clone());
+        consensus.seal(&mut block1).unwrap();
+        assert!(consensus.verify(&block1, &genesis));
+
+        block1.previous_hash = "tampered".to_string();
+        assert!(!consensus.verify(&block1, &genesis));
+    }
+}
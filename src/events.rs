@@ -0,0 +1,67 @@
+use std::error::Error;
+
+use futures_util::SinkExt;
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::blockchain::Block;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PipelineStatus {
+    Validating,
+    Committed,
+    Rejected(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ChainEvent {
+    BlockCommitted(Block),
+    AnomalyDetected { device_id: String, index: usize, value: f64 },
+    ValidationStatus(PipelineStatus),
+}
+
+// Forwards every event broadcast on `tx` to each connected WebSocket client as JSON.
+pub async fn serve_websocket(addr: &str, tx: broadcast::Sender<ChainEvent>) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(addr).await?;
+    println!("Chain event WebSocket server listening on {}", addr);
+
+    loop {
+        let (stream, peer_addr) = listener.accept().await?;
+        let mut rx = tx.subscribe();
+
+        tokio::spawn(async move {
+            let ws_stream = match tokio_tungstenite::accept_async(stream).await {
+                Ok(ws_stream) => ws_stream,
+                Err(e) => {
+                    println!("WebSocket handshake with {} failed: {}", peer_addr, e);
+                    return;
+                }
+            };
+
+            let (mut write, _) = futures_util::StreamExt::split(ws_stream);
+
+            loop {
+                let event = match rx.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        println!("WebSocket client {} lagged; skipped {} events", peer_addr, skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                let payload = match serde_json::to_string(&event) {
+                    Ok(json) => json,
+                    Err(_) => continue,
+                };
+
+                if write.send(Message::Text(payload)).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::blockchain::Block;
+use crate::iot_device::SensorData;
+
+#[derive(Clone, Debug)]
+pub struct Analysis {
+    pub block_index: usize,
+    pub timestamp: u64,
+    pub text: String,
+}
+
+enum Decoded {
+    Readings(Vec<SensorData>),
+    Analysis(String),
+    Unrecognized,
+}
+
+fn decode_block_data(data: &str) -> Decoded {
+    if let Some(text) = data.strip_prefix("ANALYSIS: ") {
+        return Decoded::Analysis(text.to_string());
+    }
+
+    if let Ok(reading) = serde_json::from_str::<SensorData>(data) {
+        return Decoded::Readings(vec![reading]);
+    }
+
+    if let Ok(readings) = serde_json::from_str::<Vec<SensorData>>(data) {
+        return Decoded::Readings(readings);
+    }
+
+    Decoded::Unrecognized
+}
+
+pub struct Store {
+    by_device: HashMap<String, Vec<SensorData>>,
+    by_timestamp: BTreeMap<u64, Vec<SensorData>>,
+    analyses: Vec<Analysis>,
+}
+
+impl Store {
+    pub fn new() -> Self {
+        Store {
+            by_device: HashMap::new(),
+            by_timestamp: BTreeMap::new(),
+            analyses: Vec::new(),
+        }
+    }
+
+    pub fn from_chain(chain: &[Block]) -> Self {
+        let mut store = Store::new();
+        for block in chain {
+            store.index_new_block(block);
+        }
+        store
+    }
+
+    pub fn index_new_block(&mut self, block: &Block) {
+        if !block.readings.is_empty() {
+            for reading in block.readings.clone() {
+                self.index_reading(reading);
+            }
+            return;
+        }
+
+        match decode_block_data(&block.data) {
+            Decoded::Readings(readings) => {
+                for reading in readings {
+                    self.index_reading(reading);
+                }
+            }
+            Decoded::Analysis(text) => {
+                self.analyses.push(Analysis {
+                    block_index: block.index,
+                    timestamp: block.timestamp,
+                    text,
+                });
+            }
+            Decoded::Unrecognized => {}
+        }
+    }
+
+    fn index_reading(&mut self, reading: SensorData) {
+        self.by_device.entry(reading.device_id.clone()).or_default().push(reading.clone());
+        self.by_timestamp.entry(reading.timestamp).or_default().push(reading);
+    }
+
+    pub fn readings_for_device(&self, device_id: &str) -> Vec<SensorData> {
+        self.by_device.get(device_id).cloned().unwrap_or_default()
+    }
+
+    pub fn readings_between(&self, start: u64, end: u64) -> Vec<SensorData> {
+        self.by_timestamp
+            .range(start..=end)
+            .flat_map(|(_, readings)| readings.iter().cloned())
+            .collect()
+    }
+
+    pub fn latest_analysis(&self) -> Option<&Analysis> {
+        self.analyses.last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::Block;
+
+    fn reading(device_id: &str, temperature: f64, timestamp: u64) -> SensorData {
+        SensorData {
+            device_id: device_id.to_string(),
+            temperature,
+            humidity: 40.0,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn indexes_readings_by_device_and_timestamp() {
+        let mut store = Store::new();
+        let block = Block::new_with_readings(
+            1,
+            100,
+            String::new(),
+            "prev".to_string(),
+            vec![reading("d1", 21.0, 10), reading("d2", 22.0, 20)],
+        );
+
+        store.index_new_block(&block);
+
+        assert_eq!(store.readings_for_device("d1").len(), 1);
+        assert_eq!(store.readings_for_device("d2").len(), 1);
+        assert!(store.readings_for_device("d3").is_empty());
+        assert_eq!(store.readings_between(0, 15).len(), 1);
+        assert_eq!(store.readings_between(0, 30).len(), 2);
+    }
+
+    #[test]
+    fn indexes_analysis_blocks_and_reports_the_latest() {
+        let mut store = Store::new();
+        let first = Block::new(1, 100, "ANALYSIS: first".to_string(), "prev".to_string());
+        let second = Block::new(2, 200, "ANALYSIS: second".to_string(), first.hash.clone());
+
+        store.index_new_block(&first);
+        store.index_new_block(&second);
+
+        let latest = store.latest_analysis().expect("an analysis was indexed");
+        assert_eq!(latest.block_index, 2);
+        assert_eq!(latest.text, "second");
+    }
+
+    #[test]
+    fn from_chain_matches_incremental_indexing() {
+        let blocks = vec![
+            Block::new_with_readings(1, 100, String::new(), "prev".to_string(), vec![reading("d1", 21.0, 10)]),
+            Block::new(2, 200, "ANALYSIS: summary".to_string(), "prev2".to_string()),
+        ];
+
+        let store = Store::from_chain(&blocks);
+
+        let mut incremental = Store::new();
+        for block in &blocks {
+            incremental.index_new_block(block);
+        }
+
+        assert_eq!(store.readings_for_device("d1").len(), 1);
+        assert_eq!(store.latest_analysis().unwrap().text, "summary");
+        assert_eq!(
+            store.readings_for_device("d1").len(),
+            incremental.readings_for_device("d1").len()
+        );
+        assert_eq!(
+            store.latest_analysis().unwrap().text,
+            incremental.latest_analysis().unwrap().text
+        );
+    }
+}
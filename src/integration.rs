@@ -1,4 +1,6 @@
 use crate::blockchain::Blockchain;
+use crate::events::{ChainEvent, PipelineStatus};
+use crate::indexer::Store;
 use crate::iot_device::{IoTDevice, SensorData};
 use crate::ai_model::{LinearRegressionModel, detect_anomalies};
 use crate::llm_integration::LLMAnalyzer;
@@ -6,6 +8,41 @@ use std::time::Duration;
 use std::env;
 use std::error::Error;
 
+fn spawn_event_logger(mut events: tokio::sync::broadcast::Receiver<ChainEvent>) {
+    use tokio::sync::broadcast::error::RecvError;
+
+    tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(skipped)) => {
+                    println!("Event logger lagged; skipped {} events", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            match event {
+                ChainEvent::ValidationStatus(PipelineStatus::Validating) => {
+                    println!("Validating next block...");
+                }
+                ChainEvent::ValidationStatus(PipelineStatus::Committed) => {
+                    println!("Block passed validation");
+                }
+                ChainEvent::ValidationStatus(PipelineStatus::Rejected(reason)) => {
+                    println!("WARNING: block rejected: {}", reason);
+                }
+                ChainEvent::BlockCommitted(block) => {
+                    println!("Added to blockchain: {}", block);
+                }
+                ChainEvent::AnomalyDetected { device_id, index, value } => {
+                    println!("Anomaly detected on {} (reading #{}): {:.1}", device_id, index, value);
+                }
+            }
+        }
+    });
+}
+
 pub async fn run_system() -> Result<(), Box<dyn Error>> {
     // Get API key - now using GROQ_API_KEY instead of OPENAI_API_KEY
     let api_key = env::var("GROQ_API_KEY").unwrap_or_else(|_| {
@@ -15,13 +52,27 @@ pub async fn run_system() -> Result<(), Box<dyn Error>> {
     
     // Initialize blockchain
     let mut blockchain = Blockchain::new();
-    
+    spawn_event_logger(blockchain.subscribe());
+
+    // Optional: stream the same events to a WebSocket dashboard if an address is configured
+    if let Ok(ws_addr) = env::var("CHAIN_EVENTS_WS_ADDR") {
+        let event_sender = blockchain.event_sender();
+        tokio::spawn(async move {
+            if let Err(e) = crate::events::serve_websocket(&ws_addr, event_sender).await {
+                println!("Chain event WebSocket server error: {}", e);
+            }
+        });
+    }
+
     // Initialize IoT devices
     let devices = vec![
         IoTDevice::new("device_001".to_string()),
         IoTDevice::new("device_002".to_string()),
     ];
     
+    // Indexes chain data into typed, queryable records as blocks are added
+    let mut store = Store::new();
+
     // Initialize AI model
     let mut model = LinearRegressionModel::new();
     
@@ -51,11 +102,11 @@ pub async fn run_system() -> Result<(), Box<dyn Error>> {
             
             println!("Reading: {}", reading.to_string());
             
-            // Add to blockchain
             let block_data = reading.to_json();
-            let block = blockchain.add_block(block_data);
-            println!("Added to blockchain: {}", block);
-            
+            if let Some(block) = blockchain.add_block(block_data) {
+                store.index_new_block(block);
+            }
+
             // Store data for analysis
             all_sensor_data.push(reading.clone());
             temperature_data.push(reading.temperature);
@@ -73,10 +124,14 @@ pub async fn run_system() -> Result<(), Box<dyn Error>> {
             // Train model on temperature data
             model.train(&x_values, &temperature_data);
             
-            // Check for anomalies
+            // Check for anomalies and publish one AnomalyDetected event per flagged reading
             let anomalies = detect_anomalies(&temperature_data, 2.0);
-            if !anomalies.is_empty() {
-                println!("Detected {} temperature anomalies", anomalies.len());
+            for &index in &anomalies {
+                blockchain.publish(ChainEvent::AnomalyDetected {
+                    device_id: all_sensor_data[index].device_id.clone(),
+                    index,
+                    value: temperature_data[index],
+                });
             }
         }
         
@@ -89,18 +144,18 @@ pub async fn run_system() -> Result<(), Box<dyn Error>> {
                 Ok(analysis) if !analysis.starts_with("Failed") && !analysis.starts_with("Error") => {
                     println!("\n=== AI Analysis (Groq) ===\n{}\n", analysis);
                     
-                    // Add the analysis to the blockchain
-                    let analysis_block = blockchain.add_block(format!("ANALYSIS: {}", analysis));
-                    println!("Added analysis to blockchain: {}", analysis_block);
+                    if let Some(block) = blockchain.add_block(format!("ANALYSIS: {}", analysis)) {
+                        store.index_new_block(block);
+                    }
                 },
                 _ => {
                     // Use fallback analysis if Groq API call fails
                     let fallback = llm_analyzer.fallback_analysis(&all_sensor_data);
                     println!("\n=== Fallback Analysis ===\n{}\n", fallback);
-                    
-                    // Add the fallback analysis to the blockchain
-                    let analysis_block = blockchain.add_block(format!("ANALYSIS: {}", fallback));
-                    println!("Added fallback analysis to blockchain: {}", analysis_block);
+
+                    if let Some(block) = blockchain.add_block(format!("ANALYSIS: {}", fallback)) {
+                        store.index_new_block(block);
+                    }
                 }
             }
         }
@@ -116,6 +171,17 @@ pub async fn run_system() -> Result<(), Box<dyn Error>> {
     
     println!("\nFinal blockchain length: {}", blockchain.get_chain_length());
     println!("Blockchain is valid: {}", blockchain.is_valid());
-    
+
+    for device in &devices {
+        println!(
+            "Indexed {} readings for {}",
+            store.readings_for_device(&device.id).len(),
+            device.id
+        );
+    }
+    if let Some(analysis) = store.latest_analysis() {
+        println!("Latest analysis (block #{}): {}", analysis.block_index, analysis.text);
+    }
+
     Ok(())
 }
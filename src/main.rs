@@ -1,4 +1,7 @@
 mod blockchain;
+mod consensus;
+mod events;
+mod indexer;
 mod iot_device;
 mod ai_model;
 mod integration;